@@ -1,301 +1,1111 @@
-use std::{
-    cmp::max,
-    mem::{replace, swap, take},
-};
-
-type ChildNode<T> = Box<BSTNode<T>>;
-
-// AVL tree node
-#[derive(Debug, Default)]
-enum BSTNode<T: Ord> {
-    #[default]
-    Nil,
-    Node {
-        left: ChildNode<T>,
-        right: ChildNode<T>,
-        height: i32,
-        value: T,
-    },
+use std::cmp::{max, Ordering};
+use std::rc::Rc;
+
+// Sentinel standing in for a null child/root index.
+const NULL: u32 = u32::MAX;
+
+fn remap(idx: u32, offset: u32) -> u32 {
+    if idx == NULL {
+        NULL
+    } else {
+        idx + offset
+    }
+}
+
+// AVL tree node, stored in a `BST`'s pool and addressed by `u32` index
+// instead of through a `Box`.
+#[derive(Debug, Clone)]
+struct Node<T: Ord + Clone> {
+    left: u32,
+    right: u32,
+    height: i32,
+    size: u32,
+    value: T,
+}
+
+// A pool slot is either a live node or a link in the free-list of reclaimed
+// slots left behind by deletes.
+#[derive(Debug, Clone)]
+enum Slot<T: Ord + Clone> {
+    Free(u32),
+    Occupied(Node<T>),
+}
+
+// Self-balancing AVL tree, backed by a single `Vec<Slot<T>>` node pool rather
+// than per-node heap allocations. Children are `u32` indices into the pool;
+// `NULL` plays the role the old `Nil` variant played. Deleted slots are
+// pushed onto a free-list and reused by later inserts.
+//
+// The pool is `Rc`-shared rather than uniquely owned: cloning a `BST`, or
+// splitting one into two, just bumps a refcount (O(1)) instead of eagerly
+// copying every node. A mutation (`node_mut`/`alloc`/`free`, via
+// `Rc::make_mut`) only pays for a deep copy the moment the pool actually
+// turns out to be shared, and is free otherwise.
+#[derive(Debug, Clone)]
+pub struct BST<T: Ord + Clone> {
+    pool: Rc<Vec<Slot<T>>>,
+    free_head: u32,
+    root: u32,
+    size: u32,
 }
 
-impl<T: Ord> BSTNode<T> {
-    pub fn new(value: T) -> Self {
-        Self::Node {
-            left: Box::new(BSTNode::Nil),
-            right: Box::new(BSTNode::Nil),
+impl<T: Ord + Clone> BST<T> {
+    pub fn new() -> Self {
+        BST {
+            pool: Rc::new(Vec::new()),
+            free_head: NULL,
+            root: NULL,
+            size: 0,
+        }
+    }
+
+    // Preallocates pool space for `capacity` nodes, for bulk loads.
+    pub fn with_capacity(capacity: usize) -> Self {
+        BST {
+            pool: Rc::new(Vec::with_capacity(capacity)),
+            free_head: NULL,
+            root: NULL,
+            size: 0,
+        }
+    }
+
+    fn node(&self, idx: u32) -> &Node<T> {
+        match &self.pool[idx as usize] {
+            Slot::Occupied(node) => node,
+            Slot::Free(_) => panic!("accessed a freed BST node"),
+        }
+    }
+
+    // Mutable node access. Clones the whole pool the first time it's called
+    // while the pool is shared with another `BST` (e.g. right after a
+    // `clone()` or a `split`); every subsequent call on this tree is free.
+    fn node_mut(&mut self, idx: u32) -> &mut Node<T> {
+        match &mut Rc::make_mut(&mut self.pool)[idx as usize] {
+            Slot::Occupied(node) => node,
+            Slot::Free(_) => panic!("accessed a freed BST node"),
+        }
+    }
+
+    // Allocates a new leaf node, reusing a reclaimed slot if one is free.
+    fn alloc(&mut self, value: T) -> u32 {
+        let node = Node {
+            left: NULL,
+            right: NULL,
             height: 0,
+            size: 1,
             value,
+        };
+        if self.free_head != NULL {
+            let idx = self.free_head;
+            let pool = Rc::make_mut(&mut self.pool);
+            self.free_head = match pool[idx as usize] {
+                Slot::Free(next) => next,
+                Slot::Occupied(_) => unreachable!("free-list pointed at an occupied slot"),
+            };
+            pool[idx as usize] = Slot::Occupied(node);
+            idx
+        } else {
+            let pool = Rc::make_mut(&mut self.pool);
+            let idx = pool.len() as u32;
+            pool.push(Slot::Occupied(node));
+            idx
         }
     }
 
-    fn contains(self: &ChildNode<T>, value: &T) -> bool {
-        return match **self {
-            Self::Nil => false,
-            Self::Node {
-                ref left,
-                ref right,
-                value: ref node_value,
-                ..
-            } => {
-                if value == node_value {
-                    true
-                } else if value > node_value {
-                    right.contains(value)
-                } else {
-                    left.contains(value)
-                }
-            }
+    // Frees `idx`'s slot for reuse, returning the value it held.
+    fn free(&mut self, idx: u32) -> T {
+        let prev_free = self.free_head;
+        let pool = Rc::make_mut(&mut self.pool);
+        let node = match std::mem::replace(&mut pool[idx as usize], Slot::Free(prev_free)) {
+            Slot::Occupied(node) => node,
+            Slot::Free(_) => panic!("double free of a BST node"),
+        };
+        self.free_head = idx;
+        node.value
+    }
+
+    fn get_height(&self, idx: u32) -> i32 {
+        if idx == NULL {
+            -1
+        } else {
+            self.node(idx).height
+        }
+    }
+
+    fn get_size(&self, idx: u32) -> u32 {
+        if idx == NULL {
+            0
+        } else {
+            self.node(idx).size
+        }
+    }
+
+    fn update_height(&mut self, idx: u32) {
+        if idx == NULL {
+            return;
+        }
+        let (left, right) = {
+            let node = self.node(idx);
+            (node.left, node.right)
         };
+        self.node_mut(idx).height = max(self.get_height(left), self.get_height(right)) + 1;
+    }
+
+    fn update_size(&mut self, idx: u32) {
+        if idx == NULL {
+            return;
+        }
+        let (left, right) = {
+            let node = self.node(idx);
+            (node.left, node.right)
+        };
+        self.node_mut(idx).size = self.get_size(left) + self.get_size(right) + 1;
+    }
+
+    fn is_imbalanced(&self, idx: u32) -> bool {
+        if idx == NULL {
+            return false;
+        }
+        let node = self.node(idx);
+        self.get_height(node.left).abs_diff(self.get_height(node.right)) > 1
     }
 
-    // Returns true if the element inserts successfully
-    fn insert_balanced(self: &mut ChildNode<T>, new_value: T) -> bool {
-        match **self {
-            Self::Nil => {
-                **self = Self::new(new_value);
+    fn left_heavy(&self, idx: u32) -> bool {
+        if idx == NULL {
+            return false;
+        }
+        let node = self.node(idx);
+        self.get_height(node.left) > self.get_height(node.right)
+    }
+
+    fn right_heavy(&self, idx: u32) -> bool {
+        if idx == NULL {
+            return false;
+        }
+        let node = self.node(idx);
+        self.get_height(node.right) > self.get_height(node.left)
+    }
+
+    // Returns the new subtree root after rotating `idx` left.
+    fn rotate_left(&mut self, idx: u32) -> u32 {
+        let new_root = self.node(idx).right;
+        let rl = self.node(new_root).left;
+
+        self.node_mut(idx).right = rl;
+        self.node_mut(new_root).left = idx;
+
+        self.update_height(idx);
+        self.update_size(idx);
+        self.update_height(new_root);
+        self.update_size(new_root);
+        new_root
+    }
+
+    // Returns the new subtree root after rotating `idx` right.
+    fn rotate_right(&mut self, idx: u32) -> u32 {
+        let new_root = self.node(idx).left;
+        let lr = self.node(new_root).right;
+
+        self.node_mut(idx).left = lr;
+        self.node_mut(new_root).right = idx;
+
+        self.update_height(idx);
+        self.update_size(idx);
+        self.update_height(new_root);
+        self.update_size(new_root);
+        new_root
+    }
+
+    // Returns the new subtree root after rebalancing `idx` (a no-op if it's
+    // already balanced).
+    fn rebalance(&mut self, idx: u32) -> u32 {
+        if !self.is_imbalanced(idx) {
+            return idx;
+        }
+
+        if self.left_heavy(idx) {
+            let left = self.node(idx).left;
+            if self.left_heavy(left) {
+                self.rotate_right(idx)
+            } else {
+                let new_left = self.rotate_left(left);
+                self.node_mut(idx).left = new_left;
+                self.rotate_right(idx)
             }
-            Self::Node {
-                ref mut left,
-                ref mut right,
-                ref value,
-                ..
-            } => {
-                if new_value == *value {
-                    return false; // no-op
-                }
-                if new_value > *value {
-                    right.insert_balanced(new_value);
-                } else {
-                    left.insert_balanced(new_value);
-                }
-                self.update_height();
+        } else {
+            let right = self.node(idx).right;
+            if self.right_heavy(right) {
+                self.rotate_left(idx)
+            } else {
+                let new_right = self.rotate_right(right);
+                self.node_mut(idx).right = new_right;
+                self.rotate_left(idx)
             }
         }
+    }
 
-        self.rebalance();
-        true
-    }
-
-    // returns true if value was deleted, false if not present
-    pub fn delete_balanced(self: &mut ChildNode<T>, value: &T) -> bool {
-        match **self {
-            Self::Nil => false,
-            Self::Node {
-                ref mut left,
-                ref mut right,
-                value: ref mut node_value,
-                ..
-            } => {
-                let deleted = if value < node_value {
-                    left.delete_balanced(value)
-                } else if value > node_value {
-                    right.delete_balanced(value)
-                } else {
-                    // delete this very node
-                    let has_left = !matches!(**left, BSTNode::Nil);
-                    let has_right = !matches!(**right, BSTNode::Nil);
-
-                    match (has_left, has_right) {
-                        (false, false) => {
-                            **self = Self::Nil;
-                        }
-                        (false, true) => *self = take(self.get_right()),
-                        (true, false) => *self = take(self.get_left()),
-                        (true, true) => {
-                            let smallest_node = right.take_smallest_in_subtree();
-
-                            if let BSTNode::Node {
-                                value: smallest_value,
-                                ..
-                            } = *smallest_node
-                            {
-                                *node_value = smallest_value;
-                            }
-                        }
-                    }
-                    true
-                };
+    fn contains_at(&self, idx: u32, value: &T) -> bool {
+        if idx == NULL {
+            return false;
+        }
+        let node = self.node(idx);
+        match value.cmp(&node.value) {
+            Ordering::Equal => true,
+            Ordering::Greater => self.contains_at(node.right, value),
+            Ordering::Less => self.contains_at(node.left, value),
+        }
+    }
 
-                if deleted {
-                    self.update_height();
-                    self.rebalance();
+    pub fn contains(&self, value: &T) -> bool {
+        self.contains_at(self.root, value)
+    }
+
+    // Returns the new subtree root and whether `value` was newly inserted.
+    fn insert_at(&mut self, idx: u32, value: T) -> (u32, bool) {
+        if idx == NULL {
+            return (self.alloc(value), true);
+        }
+
+        match value.cmp(&self.node(idx).value) {
+            Ordering::Equal => (idx, false),
+            Ordering::Greater => {
+                let right = self.node(idx).right;
+                let (new_right, inserted) = self.insert_at(right, value);
+                self.node_mut(idx).right = new_right;
+                if inserted {
+                    self.update_height(idx);
+                    self.update_size(idx);
+                }
+                (self.rebalance(idx), inserted)
+            }
+            Ordering::Less => {
+                let left = self.node(idx).left;
+                let (new_left, inserted) = self.insert_at(left, value);
+                self.node_mut(idx).left = new_left;
+                if inserted {
+                    self.update_height(idx);
+                    self.update_size(idx);
                 }
-                deleted
+                (self.rebalance(idx), inserted)
             }
         }
     }
 
-    // Returns true if the node's left subtree height is more than 1 away from its right subtree height
-    fn is_imbalanced(self: &ChildNode<T>) -> bool {
-        match **self {
-            Self::Nil => false,
-            Self::Node {
-                ref left,
-                ref right,
-                ..
-            } => left.get_height().abs_diff(right.get_height()) > 1,
+    pub fn insert(&mut self, value: T) -> bool {
+        let (new_root, inserted) = self.insert_at(self.root, value);
+        self.root = new_root;
+        if inserted {
+            self.size += 1;
         }
+        inserted
     }
 
-    fn left_heavy(self: &ChildNode<T>) -> bool {
-        match **self {
-            Self::Nil => false,
-            Self::Node {
-                ref left,
-                ref right,
-                ..
-            } => left.get_height() > right.get_height(),
+    // Removes and returns the smallest node of the subtree rooted at `idx`,
+    // returning the new subtree root alongside it.
+    fn take_smallest(&mut self, idx: u32) -> (u32, u32) {
+        let left = self.node(idx).left;
+        if left == NULL {
+            let right = self.node(idx).right;
+            (right, idx)
+        } else {
+            let (new_left, smallest) = self.take_smallest(left);
+            self.node_mut(idx).left = new_left;
+            self.update_height(idx);
+            self.update_size(idx);
+            (self.rebalance(idx), smallest)
         }
     }
 
-    fn right_heavy(self: &ChildNode<T>) -> bool {
-        match **self {
-            Self::Nil => false,
-            Self::Node {
-                ref left,
-                ref right,
-                ..
-            } => right.get_height() > left.get_height(),
+    // Returns the new subtree root and whether `value` was deleted.
+    fn delete_at(&mut self, idx: u32, value: &T) -> (u32, bool) {
+        if idx == NULL {
+            return (NULL, false);
         }
-    }
 
-    fn get_height(self: &ChildNode<T>) -> i32 {
-        match **self {
-            Self::Nil => -1,
-            Self::Node { height, .. } => height,
+        let (new_idx, deleted) = match value.cmp(&self.node(idx).value) {
+            Ordering::Less => {
+                let left = self.node(idx).left;
+                let (new_left, deleted) = self.delete_at(left, value);
+                self.node_mut(idx).left = new_left;
+                (idx, deleted)
+            }
+            Ordering::Greater => {
+                let right = self.node(idx).right;
+                let (new_right, deleted) = self.delete_at(right, value);
+                self.node_mut(idx).right = new_right;
+                (idx, deleted)
+            }
+            Ordering::Equal => {
+                let (left, right) = {
+                    let node = self.node(idx);
+                    (node.left, node.right)
+                };
+                let new_idx = match (left != NULL, right != NULL) {
+                    (false, false) => {
+                        self.free(idx);
+                        NULL
+                    }
+                    (false, true) => {
+                        self.free(idx);
+                        right
+                    }
+                    (true, false) => {
+                        self.free(idx);
+                        left
+                    }
+                    (true, true) => {
+                        let (new_right, smallest) = self.take_smallest(right);
+                        let smallest_value = self.free(smallest);
+                        self.node_mut(idx).value = smallest_value;
+                        self.node_mut(idx).right = new_right;
+                        idx
+                    }
+                };
+                (new_idx, true)
+            }
+        };
+
+        if new_idx == NULL {
+            return (NULL, deleted);
         }
+        if deleted {
+            self.update_height(new_idx);
+            self.update_size(new_idx);
+        }
+        (self.rebalance(new_idx), deleted)
     }
 
-    fn get_left<'a>(self: &'a mut ChildNode<T>) -> &'a mut ChildNode<T> {
-        match **self {
-            Self::Nil => panic!("tried to get left of empty BSTNode"),
-            Self::Node { ref mut left, .. } => left,
+    pub fn delete(&mut self, value: &T) -> bool {
+        let (new_root, deleted) = self.delete_at(self.root, value);
+        self.root = new_root;
+        if deleted {
+            self.size -= 1;
         }
+        deleted
     }
 
-    fn get_right<'a>(self: &'a mut ChildNode<T>) -> &'a mut ChildNode<T> {
-        match **self {
-            Self::Nil => panic!("tried to get left of empty BSTNode"),
-            Self::Node { ref mut right, .. } => right,
+    fn select_at(&self, idx: u32, index: usize) -> Option<&T> {
+        if idx == NULL {
+            return None;
         }
+        let node = self.node(idx);
+        let left_size = self.get_size(node.left) as usize;
+        match index.cmp(&left_size) {
+            Ordering::Less => self.select_at(node.left, index),
+            Ordering::Equal => Some(&node.value),
+            Ordering::Greater => self.select_at(node.right, index - left_size - 1),
+        }
+    }
+
+    // Returns the k-th smallest element (0-indexed), or None if index is out of bounds.
+    pub fn select(&self, index: usize) -> Option<&T> {
+        self.select_at(self.root, index)
     }
 
-    fn update_height(self: &mut ChildNode<T>) {
-        match **self {
-            Self::Nil => (),
-            Self::Node {
-                ref left,
-                ref right,
-                ref mut height,
-                ..
-            } => {
-                *height = max(left.get_height(), right.get_height()) + 1;
+    fn rank_at(&self, idx: u32, value: &T) -> usize {
+        if idx == NULL {
+            return 0;
+        }
+        let node = self.node(idx);
+        match value.cmp(&node.value) {
+            Ordering::Greater => {
+                self.get_size(node.left) as usize + 1 + self.rank_at(node.right, value)
             }
+            Ordering::Less => self.rank_at(node.left, value),
+            Ordering::Equal => self.get_size(node.left) as usize,
+        }
+    }
+
+    // Returns the number of elements strictly less than `value`.
+    pub fn rank(&self, value: &T) -> usize {
+        self.rank_at(self.root, value)
+    }
+
+    // Returns an iterator over the elements in sorted order.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter::new(self)
+    }
+
+    // Joins two subtrees known to straddle `mid` (every element reachable
+    // from `left` is less than `mid`, every element reachable from `right` is
+    // greater) into one AVL tree in O(|height(left) - height(right)|).
+    fn join_at(&mut self, left: u32, mid: T, right: u32) -> u32 {
+        if left == NULL {
+            let (new_right, _) = self.insert_at(right, mid);
+            return new_right;
+        }
+        if right == NULL {
+            let (new_left, _) = self.insert_at(left, mid);
+            return new_left;
+        }
+
+        if self.get_height(left) >= self.get_height(right) {
+            self.join_right(left, mid, right)
+        } else {
+            self.join_left(left, mid, right)
         }
     }
 
-    fn rotate_left(self: &mut ChildNode<T>) {
-        let rl = take(self.get_right().get_left());
+    // Assumes `height(left) >= height(right)`: walks down the right spine of
+    // `left` until a node short enough to sit next to `right` is found, grafts
+    // a new `mid` node there, then rebalances back up the return path.
+    fn join_right(&mut self, left: u32, mid: T, right: u32) -> u32 {
+        let lr = self.node(left).right;
 
-        let right = replace(self.get_right(), rl);
-        let mut s = replace(self, right);
-        swap(self.get_left(), &mut s);
+        let new_right = if self.get_height(lr) <= self.get_height(right) + 1 {
+            let pivot = self.alloc(mid);
+            self.node_mut(pivot).left = lr;
+            self.node_mut(pivot).right = right;
+            self.update_height(pivot);
+            self.update_size(pivot);
+            pivot
+        } else {
+            self.join_right(lr, mid, right)
+        };
 
-        self.get_left().update_height();
-        self.update_height();
+        self.node_mut(left).right = new_right;
+        self.update_height(left);
+        self.update_size(left);
+        self.rebalance(left)
     }
 
-    fn rotate_right(self: &mut ChildNode<T>) {
-        let lr = take(self.get_left().get_right());
+    // Symmetric to `join_right`: assumes `height(right) >= height(left)` and
+    // walks down the left spine of `right`.
+    fn join_left(&mut self, left: u32, mid: T, right: u32) -> u32 {
+        let rl = self.node(right).left;
 
-        let left = replace(self.get_left(), lr);
-        let mut s = replace(self, left);
-        swap(self.get_right(), &mut s);
+        let new_left = if self.get_height(rl) <= self.get_height(left) + 1 {
+            let pivot = self.alloc(mid);
+            self.node_mut(pivot).left = left;
+            self.node_mut(pivot).right = rl;
+            self.update_height(pivot);
+            self.update_size(pivot);
+            pivot
+        } else {
+            self.join_left(left, mid, rl)
+        };
 
-        self.get_right().update_height();
-        self.update_height();
+        self.node_mut(right).left = new_left;
+        self.update_height(right);
+        self.update_size(right);
+        self.rebalance(right)
     }
 
-    fn take_smallest_in_subtree(self: &mut ChildNode<T>) -> ChildNode<T> {
-        match **self {
-            Self::Nil => panic!("empty subtree"),
-            Self::Node { ref mut left, .. } => {
-                if let Self::Nil = **left {
-                    // smallest found
-                    let right_child = take(self.get_right());
+    // Joins `left`, `mid` and `right` into one tree. Every element of `left`
+    // must be less than `mid`, and every element of `right` must be greater
+    // than `mid`.
+    //
+    // When `left` and `right` still share the same pool -- the common case,
+    // since `split` hands back two trees that share the one they came from --
+    // this is genuinely O(log n): no copying happens, `Rc::ptr_eq` catches
+    // the shared pool and the join walk (O(|height(left) - height(right)|))
+    // is the only work done. Joining two trees that grew from independent
+    // pools still needs every slot of `right`'s pool copied and
+    // index-remapped into `left`'s first (O(|right|)) -- two disjoint index
+    // spaces can't be unified for free.
+    pub fn join(left: BST<T>, mid: T, right: BST<T>) -> BST<T> {
+        if Rc::ptr_eq(&left.pool, &right.pool) {
+            let size = left.size + right.size + 1;
+            let free_head = left.free_head;
+            let left_root = left.root;
+            let right_root = right.root;
+            let BST { pool, .. } = left;
+            // `right` shares the same pool `left` just handed over; dropping
+            // it explicitly (rather than letting an unused `..` remainder
+            // linger until the end of this scope) is what lets `Rc::make_mut`
+            // below see a unique owner and skip the copy.
+            drop(right);
+
+            let mut joined = BST {
+                pool,
+                free_head,
+                root: NULL,
+                size,
+            };
+            joined.root = joined.join_at(left_root, mid, right_root);
+            return joined;
+        }
 
-                    let smallest_node = take(self);
+        let size = left.size + right.size + 1;
+        let BST {
+            mut pool,
+            free_head: left_free,
+            root: left_root,
+            ..
+        } = left;
+        let BST {
+            pool: right_pool,
+            free_head: right_free,
+            root: right_root,
+            ..
+        } = right;
 
-                    **self = *right_child;
-                    smallest_node
-                } else {
-                    let smallest = left.take_smallest_in_subtree();
-                    self.update_height();
-                    self.rebalance();
-                    smallest
+        // The two trees own independent pools; splice `right`'s into `left`'s,
+        // remapping every index it contains by `left`'s original length.
+        let offset = pool.len() as u32;
+        let right_pool = Rc::try_unwrap(right_pool).unwrap_or_else(|shared| (*shared).clone());
+        let pool_mut = Rc::make_mut(&mut pool);
+        pool_mut.reserve(right_pool.len());
+        for slot in right_pool {
+            pool_mut.push(match slot {
+                Slot::Free(next) => Slot::Free(remap(next, offset)),
+                Slot::Occupied(mut node) => {
+                    node.left = remap(node.left, offset);
+                    node.right = remap(node.right, offset);
+                    Slot::Occupied(node)
                 }
+            });
+        }
+        let right_root = remap(right_root, offset);
+        let right_free = remap(right_free, offset);
+
+        let free_head = if left_free == NULL {
+            right_free
+        } else if right_free == NULL {
+            left_free
+        } else {
+            let pool_mut = Rc::make_mut(&mut pool);
+            let mut tail = left_free;
+            loop {
+                match pool_mut[tail as usize] {
+                    Slot::Free(NULL) => break,
+                    Slot::Free(next) => tail = next,
+                    Slot::Occupied(_) => unreachable!("free-list pointed at an occupied slot"),
+                }
+            }
+            pool_mut[tail as usize] = Slot::Free(right_free);
+            left_free
+        };
+
+        let mut joined = BST {
+            pool,
+            free_head,
+            root: NULL,
+            size,
+        };
+        joined.root = joined.join_at(left_root, mid, right_root);
+        joined
+    }
+
+    // Splits the subtree rooted at `idx` around `key`, returning (elements <
+    // key, was key present, elements > key) as roots still living in `self`'s
+    // pool. Recurses toward `key` and re-joins the untouched sibling subtree
+    // with the node's own value on the way back up.
+    fn split_at(&mut self, idx: u32, key: &T) -> (u32, bool, u32) {
+        if idx == NULL {
+            return (NULL, false, NULL);
+        }
+
+        let (left, right) = {
+            let node = self.node(idx);
+            (node.left, node.right)
+        };
+        match key.cmp(&self.node(idx).value) {
+            Ordering::Equal => {
+                self.free(idx);
+                (left, true, right)
+            }
+            Ordering::Less => {
+                let (l, found, r) = self.split_at(left, key);
+                let value = self.free(idx);
+                let joined_right = self.join_at(r, value, right);
+                (l, found, joined_right)
+            }
+            Ordering::Greater => {
+                let (l, found, r) = self.split_at(right, key);
+                let value = self.free(idx);
+                let joined_left = self.join_at(left, value, l);
+                (joined_left, found, r)
             }
         }
     }
 
-    fn rebalance(self: &mut ChildNode<T>) {
-        if !self.is_imbalanced() {
-            return;
+    // Splits this tree around `key` into (elements < key, was key present,
+    // elements > key), in O(log n). The walk down to `key` (`split_at`) is
+    // the only work: the two returned trees simply share this tree's pool
+    // (an `Rc::clone`, O(1)) with different roots, rather than each being
+    // copied out into one of their own -- the tombstoned nodes that belonged
+    // to the other half stay in the shared pool, unreachable but uncollected.
+    // A pure split-then-discard-one-half or split-then-`join` never pays for
+    // that: the untouched half is just dropped, or `join` walks back in
+    // through the same pool. But the first mutation (insert/delete) to
+    // either half while they're still shared pays a one-time copy-on-write
+    // (see `node_mut`) of the *whole original pool*, not just that half --
+    // splitting a huge tree into many small pieces and then mutating each
+    // one is no cheaper here than it was before this pool became `Rc`-shared.
+    pub fn split(mut self, key: &T) -> (BST<T>, bool, BST<T>) {
+        let (left_root, found, right_root) = self.split_at(self.root, key);
+        let left_size = self.get_size(left_root);
+        let right_size = self.get_size(right_root);
+        let BST { pool, free_head, .. } = self;
+
+        let left = BST {
+            pool: Rc::clone(&pool),
+            free_head,
+            root: left_root,
+            size: left_size,
+        };
+        let right = BST {
+            pool,
+            free_head,
+            root: right_root,
+            size: right_size,
+        };
+        (left, found, right)
+    }
+}
+
+impl<T: Ord + Clone> Default for BST<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// In-order iterator over `&T`. Maintains two explicit stacks of pool indices
+// (one built from the left spine, one from the right) and a remaining count
+// so `next`/`next_back` can meet in the middle without double-yielding.
+pub struct Iter<'a, T: Ord + Clone> {
+    tree: &'a BST<T>,
+    front_stack: Vec<u32>,
+    back_stack: Vec<u32>,
+    remaining: usize,
+}
+
+impl<'a, T: Ord + Clone> Iter<'a, T> {
+    fn new(tree: &'a BST<T>) -> Self {
+        let mut iter = Iter {
+            tree,
+            front_stack: Vec::new(),
+            back_stack: Vec::new(),
+            remaining: tree.get_size(tree.root) as usize,
+        };
+        iter.push_left_spine(tree.root);
+        iter.push_right_spine(tree.root);
+        iter
+    }
+
+    fn push_left_spine(&mut self, mut idx: u32) {
+        while idx != NULL {
+            self.front_stack.push(idx);
+            idx = self.tree.node(idx).left;
         }
+    }
 
-        if self.left_heavy() {
-            let left = self.get_left();
-            if left.left_heavy() {
-                self.rotate_right();
-            } else {
-                left.rotate_left();
-                self.rotate_right();
+    fn push_right_spine(&mut self, mut idx: u32) {
+        while idx != NULL {
+            self.back_stack.push(idx);
+            idx = self.tree.node(idx).right;
+        }
+    }
+}
+
+impl<'a, T: Ord + Clone> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let idx = self.front_stack.pop()?;
+        let node = self.tree.node(idx);
+        self.push_left_spine(node.right);
+        self.remaining -= 1;
+        Some(&node.value)
+    }
+}
+
+impl<'a, T: Ord + Clone> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let idx = self.back_stack.pop()?;
+        let node = self.tree.node(idx);
+        self.push_right_spine(node.left);
+        self.remaining -= 1;
+        Some(&node.value)
+    }
+}
+
+impl<'a, T: Ord + Clone> IntoIterator for &'a BST<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// Consuming in-order iterator over `T`.
+pub struct IntoIter<T: Ord + Clone>(std::vec::IntoIter<T>);
+
+impl<T: Ord + Clone> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl<T: Ord + Clone> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back()
+    }
+}
+
+impl<T: Ord + Clone> IntoIterator for BST<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        // Drains each node's value out by replacing its slot -- valid only
+        // because `try_unwrap` just proved this tree is the pool's sole owner.
+        fn drain_in_order<T: Ord + Clone>(pool: &mut Vec<Slot<T>>, idx: u32, values: &mut Vec<T>) {
+            if idx == NULL {
+                return;
             }
-        } else {
-            // self is right-heavy
-            let right = self.get_right();
-            if right.right_heavy() {
-                self.rotate_left();
-            } else {
-                right.rotate_right();
-                self.rotate_left();
+            let node = match std::mem::replace(&mut pool[idx as usize], Slot::Free(NULL)) {
+                Slot::Occupied(node) => node,
+                Slot::Free(_) => panic!("accessed a freed BST node"),
+            };
+            drain_in_order(pool, node.left, values);
+            values.push(node.value);
+            drain_in_order(pool, node.right, values);
+        }
+
+        // The pool is still shared with a sibling `BST` (e.g. this came out
+        // of a `split` whose other half is still alive), so each value has
+        // to be cloned out rather than moved.
+        fn clone_in_order<T: Ord + Clone>(pool: &[Slot<T>], idx: u32, values: &mut Vec<T>) {
+            if idx == NULL {
+                return;
             }
+            let node = match &pool[idx as usize] {
+                Slot::Occupied(node) => node,
+                Slot::Free(_) => panic!("accessed a freed BST node"),
+            };
+            clone_in_order(pool, node.left, values);
+            values.push(node.value.clone());
+            clone_in_order(pool, node.right, values);
+        }
+
+        let mut values = Vec::with_capacity(self.size as usize);
+        match Rc::try_unwrap(self.pool) {
+            Ok(mut pool) => drain_in_order(&mut pool, self.root, &mut values),
+            Err(shared) => clone_in_order(&shared, self.root, &mut values),
         }
+        IntoIter(values.into_iter())
     }
 }
 
-// Self-balancing AVL tree.
-#[derive(Debug)]
-pub struct BST<T: Ord> {
-    root: ChildNode<T>,
-    size: u32,
+impl<T: Ord + Clone> FromIterator<T> for BST<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut bst = BST::new();
+        bst.extend(iter);
+        bst
+    }
 }
 
-impl<T: Ord> BST<T> {
-    pub fn new() -> Self {
-        BST {
-            root: Box::new(BSTNode::Nil),
-            size: 0,
+impl<T: Ord + Clone> Extend<T> for BST<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
         }
     }
+}
 
-    pub fn contains(&self, value: &T) -> bool {
-        self.root.contains(value)
+impl<T: Ord + Clone + std::fmt::Debug> BST<T> {
+    // Renders the tree sideways for debugging: the right subtree above each
+    // node, the left subtree below, connected by box-drawing characters and
+    // indented one level per depth. Each node is annotated with its height
+    // and balance factor (`left height - right height`), since those are
+    // exactly what rotations act on.
+    pub fn display_tree(&self) -> String {
+        let mut out = String::new();
+        self.write_subtree(self.root, String::new(), true, &mut out);
+        out
     }
 
-    pub fn insert(&mut self, value: T) -> bool {
-        let inserted = self.root.insert_balanced(value);
-        if inserted {
-            self.size += 1;
+    fn write_subtree(&self, idx: u32, prefix: String, is_left: bool, out: &mut String) {
+        if idx == NULL {
+            return;
         }
-        inserted
+        let node = self.node(idx);
+        let balance_factor = self.get_height(node.left) - self.get_height(node.right);
+
+        let child_prefix = format!("{prefix}{}", if is_left { "│   " } else { "    " });
+        self.write_subtree(node.right, child_prefix, false, out);
+
+        let connector = if is_left { "└───" } else { "┌───" };
+        out.push_str(&format!(
+            "{prefix}{connector} {:?} (h={}, bf={})\n",
+            node.value, node.height, balance_factor
+        ));
+
+        let child_prefix = format!("{prefix}{}", if is_left { "    " } else { "│   " });
+        self.write_subtree(node.left, child_prefix, true, out);
     }
+}
 
-    pub fn delete(&mut self, value: &T) -> bool {
-        let deleted = self.root.delete_balanced(value);
-        if deleted {
-            self.size -= 1;
+impl<T: Ord + Clone + std::fmt::Debug> std::fmt::Display for BST<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.display_tree())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_returns_kth_smallest_in_sorted_order() {
+        let mut bst = BST::new();
+        for v in [5, 1, 9, 3, 7, 2, 8, 4, 6] {
+            bst.insert(v);
         }
-        deleted
+        for (i, expected) in (1..=9).enumerate() {
+            assert_eq!(bst.select(i), Some(&expected));
+        }
+        assert_eq!(bst.select(9), None);
+    }
+
+    #[test]
+    fn rank_counts_elements_strictly_less_than_value() {
+        let mut bst = BST::new();
+        for v in [5, 1, 9, 3, 7, 2, 8, 4, 6] {
+            bst.insert(v);
+        }
+        assert_eq!(bst.rank(&1), 0);
+        assert_eq!(bst.rank(&5), 4);
+        assert_eq!(bst.rank(&9), 8);
+        assert_eq!(bst.rank(&10), 9);
+    }
+
+    #[test]
+    fn select_and_rank_survive_deletions() {
+        let mut bst = BST::new();
+        for v in 0..20 {
+            bst.insert(v);
+        }
+        for v in (0..20).step_by(2) {
+            bst.delete(&v);
+        }
+        let odds: Vec<i32> = (1..20).step_by(2).collect();
+        for (i, expected) in odds.iter().enumerate() {
+            assert_eq!(bst.select(i), Some(expected));
+        }
+        for v in &odds {
+            assert_eq!(bst.rank(v), odds.iter().filter(|&&o| o < *v).count());
+        }
+    }
+
+    #[test]
+    fn iter_yields_elements_in_sorted_order() {
+        let bst: BST<i32> = [5, 3, 8, 1, 4, 7, 9].into_iter().collect();
+        let collected: Vec<&i32> = bst.iter().collect();
+        assert_eq!(collected, vec![&1, &3, &4, &5, &7, &8, &9]);
+    }
+
+    #[test]
+    fn double_ended_iterator_meets_in_the_middle() {
+        let bst: BST<i32> = (1..=10).collect();
+        let mut iter = bst.iter();
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next_back(), Some(&10));
+        let middle: Vec<&i32> = iter.collect();
+        assert_eq!(middle, vec![&2, &3, &4, &5, &6, &7, &8, &9]);
+    }
+
+    #[test]
+    fn into_iter_consumes_in_sorted_order() {
+        let bst: BST<i32> = [3, 1, 2].into_iter().collect();
+        let values: Vec<i32> = bst.into_iter().collect();
+        assert_eq!(values, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn extend_inserts_additional_elements() {
+        let mut bst: BST<i32> = (1..=3).collect();
+        bst.extend(4..=6);
+        let values: Vec<&i32> = bst.iter().collect();
+        assert_eq!(values, vec![&1, &2, &3, &4, &5, &6]);
+    }
+
+    #[test]
+    fn split_partitions_around_a_present_key() {
+        let bst: BST<i32> = (1..=10).collect();
+        let (left, found, right) = bst.split(&5);
+        assert!(found);
+        assert_eq!(left.iter().copied().collect::<Vec<_>>(), (1..5).collect::<Vec<_>>());
+        assert_eq!(right.iter().copied().collect::<Vec<_>>(), (6..=10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn split_reports_an_absent_key() {
+        let bst: BST<i32> = [1, 3, 5, 7].into_iter().collect();
+        let (left, found, right) = bst.split(&4);
+        assert!(!found);
+        assert_eq!(left.iter().copied().collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(right.iter().copied().collect::<Vec<_>>(), vec![5, 7]);
+    }
+
+    #[test]
+    fn join_reassembles_a_split_tree() {
+        let bst: BST<i32> = (1..=10).collect();
+        let (left, _, right) = bst.split(&5);
+        let rejoined = BST::join(left, 5, right);
+        assert_eq!(
+            rejoined.iter().copied().collect::<Vec<_>>(),
+            (1..=10).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn join_rebalances_mismatched_heights() {
+        let small: BST<i32> = std::iter::once(0).collect();
+        let large: BST<i32> = (2..=50).collect();
+        let joined = BST::join(small, 1, large);
+        assert_eq!(
+            joined.iter().copied().collect::<Vec<_>>(),
+            (0..=50).collect::<Vec<_>>()
+        );
+        // Neither input had deletes, so every occupied slot is a live node.
+        for slot in joined.pool.iter() {
+            if let Slot::Occupied(node) = slot {
+                assert!(joined.get_height(node.left).abs_diff(joined.get_height(node.right)) <= 1);
+            }
+        }
+    }
+
+    #[test]
+    fn split_shares_one_pool_between_both_halves() {
+        // No materializing: until one half is mutated, `left` and `right`
+        // are two views onto the very same pool.
+        let bst: BST<i32> = (1..=10).collect();
+        let (left, _, right) = bst.split(&5);
+        assert!(Rc::ptr_eq(&left.pool, &right.pool));
+    }
+
+    #[test]
+    fn join_of_a_splits_own_halves_skips_the_pool_copy() {
+        let bst: BST<i32> = (1..=10).collect();
+        let (left, _, right) = bst.split(&5);
+        // A raw pointer, not an `Rc` clone: checking it afterwards mustn't
+        // itself be the reason `join`'s internal `Rc::make_mut` decides to
+        // copy (an extra strong reference held across the call would).
+        let original_pool = Rc::as_ptr(&left.pool);
+        let joined = BST::join(left, 5, right);
+        // The joined tree's pool is literally the same allocation the split
+        // halves shared -- `join` took the fast, copy-free path.
+        assert!(std::ptr::eq(Rc::as_ptr(&joined.pool), original_pool));
+        assert_eq!(
+            joined.iter().copied().collect::<Vec<_>>(),
+            (1..=10).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn join_of_independently_built_trees_still_works() {
+        let left: BST<i32> = (1..=3).collect();
+        let right: BST<i32> = (5..=7).collect();
+        assert!(!Rc::ptr_eq(&left.pool, &right.pool));
+        let joined = BST::join(left, 4, right);
+        assert_eq!(
+            joined.iter().copied().collect::<Vec<_>>(),
+            (1..=7).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn mutating_one_split_half_leaves_the_other_unaffected() {
+        let bst: BST<i32> = (1..=10).collect();
+        let (mut left, _, right) = bst.split(&5);
+        assert!(Rc::ptr_eq(&left.pool, &right.pool));
+
+        left.insert(100);
+        left.delete(&1);
+
+        assert!(!Rc::ptr_eq(&left.pool, &right.pool));
+        assert_eq!(
+            left.iter().copied().collect::<Vec<_>>(),
+            vec![2, 3, 4, 100]
+        );
+        assert_eq!(right.iter().copied().collect::<Vec<_>>(), (6..=10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn display_tree_renders_every_value_with_height_and_balance() {
+        let bst: BST<i32> = [2, 1, 3].into_iter().collect();
+        let rendered = bst.display_tree();
+        for value in [1, 2, 3] {
+            assert!(rendered.contains(&format!("{value}")));
+        }
+        assert!(rendered.contains("h="));
+        assert!(rendered.contains("bf="));
+        assert_eq!(format!("{bst}"), rendered);
+    }
+
+    #[test]
+    fn with_capacity_preallocates_and_behaves_like_new() {
+        let mut bst = BST::with_capacity(64);
+        assert_eq!(bst.pool.capacity(), 64);
+        for v in 1..=20 {
+            bst.insert(v);
+        }
+        for v in 1..=20 {
+            assert!(bst.contains(&v));
+        }
+        assert_eq!(bst.iter().copied().collect::<Vec<_>>(), (1..=20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn delete_then_insert_reuses_the_free_list() {
+        let mut bst = BST::new();
+        for v in 0..100 {
+            bst.insert(v);
+        }
+        let pool_len_before = bst.pool.len();
+
+        // Delete every other element, then insert the same count back in:
+        // the new nodes should land in slots reclaimed from the deletes
+        // rather than growing the pool, and the tree should still be sound.
+        for v in (0..100).step_by(2) {
+            bst.delete(&v);
+        }
+        for v in 200..250 {
+            bst.insert(v);
+        }
+
+        assert_eq!(bst.pool.len(), pool_len_before);
+        assert_eq!(bst.size, 50 + 50);
+
+        let expected: Vec<i32> = (1..100).step_by(2).chain(200..250).collect();
+        assert_eq!(bst.iter().copied().collect::<Vec<_>>(), expected);
+        for v in (0..100).step_by(2) {
+            assert!(!bst.contains(&v));
+        }
+        for v in 200..250 {
+            assert!(bst.contains(&v));
+        }
+    }
+
+    #[test]
+    fn clone_is_independent_and_cheap_until_mutated() {
+        let original: BST<i32> = (1..=10).collect();
+        let cloned = original.clone();
+        assert!(Rc::ptr_eq(&original.pool, &cloned.pool));
+
+        let mut cloned = cloned;
+        cloned.insert(100);
+        cloned.delete(&1);
+
+        assert!(!Rc::ptr_eq(&original.pool, &cloned.pool));
+        assert_eq!(
+            original.iter().copied().collect::<Vec<_>>(),
+            (1..=10).collect::<Vec<_>>()
+        );
+        let mut expected: Vec<i32> = (2..=10).collect();
+        expected.push(100);
+        assert_eq!(cloned.iter().copied().collect::<Vec<_>>(), expected);
     }
 }
@@ -0,0 +1,632 @@
+use std::{
+    cmp::max,
+    mem::{replace, take},
+};
+
+/// Types that form a discrete, steppable sequence (integers, `char`), so that
+/// a `DietSet` can tell when two values are adjacent and merge their runs.
+pub trait Steppable: Sized {
+    fn successor(&self) -> Option<Self>;
+    fn predecessor(&self) -> Option<Self>;
+}
+
+macro_rules! impl_steppable_int {
+    ($($t:ty),* $(,)?) => {
+        $(impl Steppable for $t {
+            fn successor(&self) -> Option<Self> {
+                self.checked_add(1)
+            }
+
+            fn predecessor(&self) -> Option<Self> {
+                self.checked_sub(1)
+            }
+        })*
+    };
+}
+
+impl_steppable_int!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+impl Steppable for char {
+    fn successor(&self) -> Option<Self> {
+        char::from_u32(*self as u32 + 1)
+    }
+
+    fn predecessor(&self) -> Option<Self> {
+        (*self as u32).checked_sub(1).and_then(char::from_u32)
+    }
+}
+
+type ChildNode<T> = Box<DietNode<T>>;
+
+// AVL tree node holding an inclusive run [lo, hi] instead of a single value.
+#[derive(Debug, Default)]
+enum DietNode<T: Steppable + Ord + Clone> {
+    #[default]
+    Nil,
+    Node {
+        left: ChildNode<T>,
+        right: ChildNode<T>,
+        height: i32,
+        lo: T,
+        hi: T,
+    },
+}
+
+impl<T: Steppable + Ord + Clone> DietNode<T> {
+    fn new(value: T) -> Self {
+        Self::Node {
+            left: Box::new(Self::Nil),
+            right: Box::new(Self::Nil),
+            height: 0,
+            lo: value.clone(),
+            hi: value,
+        }
+    }
+
+    fn contains(self: &ChildNode<T>, value: &T) -> bool {
+        match **self {
+            Self::Nil => false,
+            Self::Node {
+                ref left,
+                ref right,
+                ref lo,
+                ref hi,
+                ..
+            } => {
+                if value < lo {
+                    left.contains(value)
+                } else if value > hi {
+                    right.contains(value)
+                } else {
+                    true
+                }
+            }
+        }
+    }
+
+    // Returns true if `value` was absent and is now covered by some run.
+    fn insert(self: &mut ChildNode<T>, value: T) -> bool {
+        match **self {
+            Self::Nil => {
+                **self = Self::new(value);
+                return true;
+            }
+            Self::Node {
+                ref lo, ref hi, ..
+            } => {
+                if *lo <= value && value <= *hi {
+                    return false;
+                }
+            }
+        }
+
+        let (inserted, extended_lo, extended_hi) = match **self {
+            Self::Nil => unreachable!(),
+            Self::Node {
+                ref mut left,
+                ref mut right,
+                ref mut lo,
+                ref mut hi,
+                ..
+            } => {
+                if value < *lo {
+                    if value.successor().as_ref() == Some(&*lo) {
+                        *lo = value;
+                        (true, true, false)
+                    } else {
+                        (left.insert(value), false, false)
+                    }
+                } else if hi.successor().as_ref() == Some(&value) {
+                    *hi = value;
+                    (true, false, true)
+                } else {
+                    (right.insert(value), false, false)
+                }
+            }
+        };
+
+        if extended_lo {
+            self.bridge_left();
+        }
+        if extended_hi {
+            self.bridge_right();
+        }
+        if inserted {
+            self.update_height();
+            self.rebalance();
+        }
+        inserted
+    }
+
+    // If the left subtree's rightmost run directly abuts this node's `lo`,
+    // absorb it: the absorbed run's `lo` becomes this node's new `lo`.
+    fn bridge_left(self: &mut ChildNode<T>) {
+        if let Self::Node {
+            ref mut left,
+            ref mut lo,
+            ..
+        } = **self
+        {
+            let Some(neighbor_hi) = left.max_hi() else {
+                return;
+            };
+            if neighbor_hi.successor().as_ref() != Some(&*lo) {
+                return;
+            }
+            let absorbed = left.take_largest_in_subtree();
+            if let Self::Node { lo: absorbed_lo, .. } = *absorbed {
+                *lo = absorbed_lo;
+            }
+        }
+    }
+
+    // Symmetric to `bridge_left`: absorbs the right subtree's leftmost run if
+    // it directly abuts this node's `hi`.
+    fn bridge_right(self: &mut ChildNode<T>) {
+        if let Self::Node {
+            ref mut right,
+            ref mut hi,
+            ..
+        } = **self
+        {
+            let Some(neighbor_lo) = right.min_lo() else {
+                return;
+            };
+            if hi.successor().as_ref() != Some(&neighbor_lo) {
+                return;
+            }
+            let absorbed = right.take_smallest_in_subtree();
+            if let Self::Node { hi: absorbed_hi, .. } = *absorbed {
+                *hi = absorbed_hi;
+            }
+        }
+    }
+
+    fn max_hi(self: &ChildNode<T>) -> Option<T> {
+        match **self {
+            Self::Nil => None,
+            Self::Node {
+                ref right, ref hi, ..
+            } => {
+                if matches!(**right, Self::Nil) {
+                    Some(hi.clone())
+                } else {
+                    right.max_hi()
+                }
+            }
+        }
+    }
+
+    fn min_lo(self: &ChildNode<T>) -> Option<T> {
+        match **self {
+            Self::Nil => None,
+            Self::Node {
+                ref left, ref lo, ..
+            } => {
+                if matches!(**left, Self::Nil) {
+                    Some(lo.clone())
+                } else {
+                    left.min_lo()
+                }
+            }
+        }
+    }
+
+    // Returns true if `value` was present and has now been removed.
+    fn delete(self: &mut ChildNode<T>, value: &T) -> bool {
+        match **self {
+            Self::Nil => false,
+            Self::Node {
+                ref mut left,
+                ref mut right,
+                lo: ref mut node_lo,
+                hi: ref mut node_hi,
+                ..
+            } => {
+                let deleted = if value < node_lo {
+                    left.delete(value)
+                } else if value > node_hi {
+                    right.delete(value)
+                } else if node_lo < node_hi && value == node_lo {
+                    *node_lo = value.successor().expect("lo has a successor since lo < hi");
+                    true
+                } else if node_lo < node_hi && value == node_hi {
+                    *node_hi = value.predecessor().expect("hi has a predecessor since lo < hi");
+                    true
+                } else if node_lo < node_hi {
+                    // interior value: shrink this run and push the remainder
+                    // into the right subtree as a fresh, already-disjoint run
+                    let new_right_lo =
+                        value.successor().expect("interior value has a successor");
+                    let new_hi = value
+                        .predecessor()
+                        .expect("interior value has a predecessor");
+                    let orig_hi = replace(node_hi, new_hi);
+                    right.insert_run(new_right_lo, orig_hi);
+                    true
+                } else {
+                    // singleton run: remove the whole node
+                    let has_left = !matches!(**left, Self::Nil);
+                    let has_right = !matches!(**right, Self::Nil);
+
+                    match (has_left, has_right) {
+                        (false, false) => {
+                            **self = Self::Nil;
+                        }
+                        (false, true) => *self = take(self.get_right()),
+                        (true, false) => *self = take(self.get_left()),
+                        (true, true) => {
+                            let smallest = right.take_smallest_in_subtree();
+                            if let Self::Node {
+                                lo: s_lo, hi: s_hi, ..
+                            } = *smallest
+                            {
+                                *node_lo = s_lo;
+                                *node_hi = s_hi;
+                            }
+                        }
+                    }
+                    true
+                };
+
+                if deleted {
+                    self.update_height();
+                    self.rebalance();
+                }
+                deleted
+            }
+        }
+    }
+
+    // Inserts a pre-built, already-disjoint-and-non-adjacent run as an
+    // ordinary BST node; used by `delete` to re-home the tail half of a run
+    // split by an interior deletion.
+    fn insert_run(self: &mut ChildNode<T>, new_lo: T, new_hi: T) {
+        match **self {
+            Self::Nil => {
+                **self = Self::Node {
+                    left: Box::new(Self::Nil),
+                    right: Box::new(Self::Nil),
+                    height: 0,
+                    lo: new_lo,
+                    hi: new_hi,
+                };
+                return;
+            }
+            Self::Node {
+                ref mut left,
+                ref mut right,
+                ref lo,
+                ..
+            } => {
+                if new_lo < *lo {
+                    left.insert_run(new_lo, new_hi);
+                } else {
+                    right.insert_run(new_lo, new_hi);
+                }
+            }
+        }
+        self.update_height();
+        self.rebalance();
+    }
+
+    fn is_imbalanced(self: &ChildNode<T>) -> bool {
+        match **self {
+            Self::Nil => false,
+            Self::Node {
+                ref left,
+                ref right,
+                ..
+            } => left.get_height().abs_diff(right.get_height()) > 1,
+        }
+    }
+
+    fn left_heavy(self: &ChildNode<T>) -> bool {
+        match **self {
+            Self::Nil => false,
+            Self::Node {
+                ref left,
+                ref right,
+                ..
+            } => left.get_height() > right.get_height(),
+        }
+    }
+
+    fn right_heavy(self: &ChildNode<T>) -> bool {
+        match **self {
+            Self::Nil => false,
+            Self::Node {
+                ref left,
+                ref right,
+                ..
+            } => right.get_height() > left.get_height(),
+        }
+    }
+
+    fn get_height(self: &ChildNode<T>) -> i32 {
+        match **self {
+            Self::Nil => -1,
+            Self::Node { height, .. } => height,
+        }
+    }
+
+    fn get_left<'a>(self: &'a mut ChildNode<T>) -> &'a mut ChildNode<T> {
+        match **self {
+            Self::Nil => panic!("tried to get left of empty DietNode"),
+            Self::Node { ref mut left, .. } => left,
+        }
+    }
+
+    fn get_right<'a>(self: &'a mut ChildNode<T>) -> &'a mut ChildNode<T> {
+        match **self {
+            Self::Nil => panic!("tried to get right of empty DietNode"),
+            Self::Node { ref mut right, .. } => right,
+        }
+    }
+
+    fn update_height(self: &mut ChildNode<T>) {
+        match **self {
+            Self::Nil => (),
+            Self::Node {
+                ref left,
+                ref right,
+                ref mut height,
+                ..
+            } => {
+                *height = max(left.get_height(), right.get_height()) + 1;
+            }
+        }
+    }
+
+    fn rotate_left(self: &mut ChildNode<T>) {
+        let rl = take(self.get_right().get_left());
+
+        let right = replace(self.get_right(), rl);
+        let mut s = replace(self, right);
+        std::mem::swap(self.get_left(), &mut s);
+
+        self.get_left().update_height();
+        self.update_height();
+    }
+
+    fn rotate_right(self: &mut ChildNode<T>) {
+        let lr = take(self.get_left().get_right());
+
+        let left = replace(self.get_left(), lr);
+        let mut s = replace(self, left);
+        std::mem::swap(self.get_right(), &mut s);
+
+        self.get_right().update_height();
+        self.update_height();
+    }
+
+    fn take_smallest_in_subtree(self: &mut ChildNode<T>) -> ChildNode<T> {
+        match **self {
+            Self::Nil => panic!("empty subtree"),
+            Self::Node { ref mut left, .. } => {
+                if let Self::Nil = **left {
+                    let right_child = take(self.get_right());
+                    let smallest_node = take(self);
+                    **self = *right_child;
+                    smallest_node
+                } else {
+                    let smallest = left.take_smallest_in_subtree();
+                    self.update_height();
+                    self.rebalance();
+                    smallest
+                }
+            }
+        }
+    }
+
+    fn take_largest_in_subtree(self: &mut ChildNode<T>) -> ChildNode<T> {
+        match **self {
+            Self::Nil => panic!("empty subtree"),
+            Self::Node { ref mut right, .. } => {
+                if let Self::Nil = **right {
+                    let left_child = take(self.get_left());
+                    let largest_node = take(self);
+                    **self = *left_child;
+                    largest_node
+                } else {
+                    let largest = right.take_largest_in_subtree();
+                    self.update_height();
+                    self.rebalance();
+                    largest
+                }
+            }
+        }
+    }
+
+    fn rebalance(self: &mut ChildNode<T>) {
+        if !self.is_imbalanced() {
+            return;
+        }
+
+        if self.left_heavy() {
+            let left = self.get_left();
+            if left.left_heavy() {
+                self.rotate_right();
+            } else {
+                left.rotate_left();
+                self.rotate_right();
+            }
+        } else {
+            let right = self.get_right();
+            if right.right_heavy() {
+                self.rotate_left();
+            } else {
+                right.rotate_right();
+                self.rotate_left();
+            }
+        }
+    }
+}
+
+// A set over a steppable type, storing contiguous runs as single nodes
+// instead of one node per element. Dense sets collapse to
+// O(number-of-runs) nodes while keeping the same AVL balancing machinery.
+#[derive(Debug)]
+pub struct DietSet<T: Steppable + Ord + Clone> {
+    root: ChildNode<T>,
+}
+
+impl<T: Steppable + Ord + Clone> DietSet<T> {
+    pub fn new() -> Self {
+        DietSet {
+            root: Box::new(DietNode::Nil),
+        }
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        self.root.contains(value)
+    }
+
+    pub fn insert(&mut self, value: T) -> bool {
+        self.root.insert(value)
+    }
+
+    pub fn delete(&mut self, value: &T) -> bool {
+        self.root.delete(value)
+    }
+
+    // Iterates the runs making up this set, in order, as `(lo, hi)` pairs.
+    pub fn ranges(&self) -> Ranges<'_, T> {
+        Ranges::new(&self.root)
+    }
+
+    // Iterates every individual element in sorted order.
+    pub fn iter(&self) -> Elements<'_, T> {
+        Elements {
+            ranges: self.ranges(),
+            current: None,
+        }
+    }
+}
+
+impl<T: Steppable + Ord + Clone> Default for DietSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Iterator over `(&lo, &hi)` runs, walking the tree with an explicit stack.
+pub struct Ranges<'a, T: Steppable + Ord + Clone> {
+    stack: Vec<&'a DietNode<T>>,
+}
+
+impl<'a, T: Steppable + Ord + Clone> Ranges<'a, T> {
+    fn new(root: &'a ChildNode<T>) -> Self {
+        let mut ranges = Ranges { stack: Vec::new() };
+        ranges.push_left_spine(root);
+        ranges
+    }
+
+    fn push_left_spine(&mut self, mut node: &'a DietNode<T>) {
+        while let DietNode::Node { left, .. } = node {
+            self.stack.push(node);
+            node = left;
+        }
+    }
+}
+
+impl<'a, T: Steppable + Ord + Clone> Iterator for Ranges<'a, T> {
+    type Item = (&'a T, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        let DietNode::Node { right, lo, hi, .. } = node else {
+            unreachable!("Nil nodes are never pushed onto the stack");
+        };
+        self.push_left_spine(right);
+        Some((lo, hi))
+    }
+}
+
+// Iterator over individual elements, expanding each run lazily via `Steppable`.
+pub struct Elements<'a, T: Steppable + Ord + Clone> {
+    ranges: Ranges<'a, T>,
+    current: Option<(T, &'a T)>,
+}
+
+impl<'a, T: Steppable + Ord + Clone> Iterator for Elements<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current.is_none() {
+            let (lo, hi) = self.ranges.next()?;
+            self.current = Some((lo.clone(), hi));
+        }
+        let (value, hi) = self.current.take().unwrap();
+        if &value < hi {
+            self.current = value.successor().map(|next| (next, hi));
+        }
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_merges_adjacent_runs() {
+        let mut set = DietSet::new();
+        for v in [1, 2, 3, 4, 6, 7] {
+            set.insert(v);
+        }
+        set.insert(5); // bridges [1,4] and [6,7] into [1,7]
+        assert_eq!(set.ranges().collect::<Vec<_>>(), vec![(&1, &7)]);
+    }
+
+    #[test]
+    fn contains_checks_membership_within_runs() {
+        let mut set = DietSet::new();
+        for v in [1, 2, 3, 10, 11] {
+            set.insert(v);
+        }
+        assert!(set.contains(&2));
+        assert!(set.contains(&11));
+        assert!(!set.contains(&5));
+    }
+
+    #[test]
+    fn delete_splits_an_interior_value_out_of_a_run() {
+        let mut set = DietSet::new();
+        for v in 1..=10 {
+            set.insert(v);
+        }
+        assert!(set.delete(&5));
+        assert!(!set.contains(&5));
+        assert_eq!(set.ranges().collect::<Vec<_>>(), vec![(&1, &4), (&6, &10)]);
+    }
+
+    #[test]
+    fn delete_of_a_boundary_value_shrinks_the_run() {
+        let mut set = DietSet::new();
+        for v in 1..=5 {
+            set.insert(v);
+        }
+        assert!(set.delete(&1));
+        assert!(set.delete(&5));
+        assert_eq!(set.ranges().collect::<Vec<_>>(), vec![(&2, &4)]);
+    }
+
+    #[test]
+    fn iter_expands_runs_into_individual_elements() {
+        let mut set = DietSet::new();
+        for v in [1, 2, 3, 10] {
+            set.insert(v);
+        }
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![1, 2, 3, 10]);
+    }
+
+    #[test]
+    fn char_steppable_merges_adjacent_letters() {
+        let mut set = DietSet::new();
+        for c in ['a', 'b', 'c'] {
+            set.insert(c);
+        }
+        assert_eq!(set.ranges().collect::<Vec<_>>(), vec![(&'a', &'c')]);
+    }
+}
@@ -1,6 +1,10 @@
 mod avl;
+mod diet;
+mod persistent;
 
 use crate::avl::BST;
+use crate::diet::DietSet;
+use crate::persistent::PersistentBST;
 
 // Basic use of BST functions
 fn main() {
@@ -23,5 +27,43 @@ fn main() {
     bst.delete(&1);
     bst.delete(&3);
     bst.delete(&7);
-    println!("BST operation successful!")
+    println!("BST operation successful!");
+
+    diet_set_demo();
+    persistent_bst_demo();
+}
+
+// Basic use of DietSet, showing runs merging on insert and splitting on delete
+fn diet_set_demo() {
+    let mut diet = DietSet::new();
+    for i in 1..=10 {
+        diet.insert(i);
+    }
+    diet.insert(12);
+    diet.insert(13);
+    assert_eq!(diet.ranges().collect::<Vec<_>>(), vec![(&1, &10), (&12, &13)]);
+
+    diet.delete(&5);
+    assert!(!diet.contains(&5));
+    assert!(diet.contains(&4));
+    assert!(diet.contains(&6));
+
+    let elements: Vec<i32> = diet.iter().collect();
+    assert_eq!(elements.len(), 11);
+    println!("DietSet operation successful!");
+}
+
+// Basic use of PersistentBST, showing a snapshot stay unaffected by later updates
+fn persistent_bst_demo() {
+    let v1 = PersistentBST::new().insert(1).insert(2).insert(3);
+    let snapshot = v1.snapshot();
+    let v2 = v1.insert(4);
+    let v3 = v1.delete(&2);
+
+    assert!(snapshot.contains(&1) && snapshot.contains(&2) && snapshot.contains(&3));
+    assert!(!snapshot.contains(&4));
+    assert!(v2.contains(&4));
+    assert!(v1.contains(&2));
+    assert!(!v3.contains(&2));
+    println!("PersistentBST operation successful!");
 }
@@ -0,0 +1,249 @@
+use std::{cmp::max, rc::Rc};
+
+type Link<T> = Option<Rc<Node<T>>>;
+
+// Immutable AVL tree node. Children are shared via `Rc` so that an update can
+// graft fresh nodes onto the O(log n) search path while leaving every
+// untouched sibling subtree referenced, not copied.
+struct Node<T: Ord + Clone> {
+    left: Link<T>,
+    right: Link<T>,
+    height: i32,
+    value: T,
+}
+
+fn height<T: Ord + Clone>(link: &Link<T>) -> i32 {
+    link.as_ref().map_or(-1, |node| node.height)
+}
+
+fn new_node<T: Ord + Clone>(left: Link<T>, value: T, right: Link<T>) -> Rc<Node<T>> {
+    let height = max(height(&left), height(&right)) + 1;
+    Rc::new(Node {
+        left,
+        right,
+        height,
+        value,
+    })
+}
+
+fn rotate_left<T: Ord + Clone>(node: Rc<Node<T>>) -> Rc<Node<T>> {
+    let right = node.right.clone().expect("rotate_left requires a right child");
+    let new_left = new_node(node.left.clone(), node.value.clone(), right.left.clone());
+    new_node(Some(new_left), right.value.clone(), right.right.clone())
+}
+
+fn rotate_right<T: Ord + Clone>(node: Rc<Node<T>>) -> Rc<Node<T>> {
+    let left = node.left.clone().expect("rotate_right requires a left child");
+    let new_right = new_node(left.right.clone(), node.value.clone(), node.right.clone());
+    new_node(left.left.clone(), left.value.clone(), Some(new_right))
+}
+
+fn rebalance<T: Ord + Clone>(node: Rc<Node<T>>) -> Rc<Node<T>> {
+    let balance = height(&node.left) - height(&node.right);
+    if balance > 1 {
+        let left = node.left.clone().unwrap();
+        if height(&left.left) >= height(&left.right) {
+            rotate_right(node)
+        } else {
+            let new_left = rotate_left(left);
+            rotate_right(new_node(Some(new_left), node.value.clone(), node.right.clone()))
+        }
+    } else if balance < -1 {
+        let right = node.right.clone().unwrap();
+        if height(&right.right) >= height(&right.left) {
+            rotate_left(node)
+        } else {
+            let new_right = rotate_right(right);
+            rotate_left(new_node(node.left.clone(), node.value.clone(), Some(new_right)))
+        }
+    } else {
+        node
+    }
+}
+
+fn contains<T: Ord + Clone>(link: &Link<T>, value: &T) -> bool {
+    match link {
+        None => false,
+        Some(node) => {
+            if value == &node.value {
+                true
+            } else if value > &node.value {
+                contains(&node.right, value)
+            } else {
+                contains(&node.left, value)
+            }
+        }
+    }
+}
+
+// Returns the new subtree and whether `value` was newly inserted. Shares
+// every subtree not on the search path with the input.
+fn insert<T: Ord + Clone>(link: &Link<T>, value: T) -> (Link<T>, bool) {
+    match link {
+        None => (Some(new_node(None, value, None)), true),
+        Some(node) => {
+            if value == node.value {
+                return (Some(node.clone()), false);
+            }
+            if value > node.value {
+                let (new_right, inserted) = insert(&node.right, value);
+                if !inserted {
+                    return (Some(node.clone()), false);
+                }
+                let grafted = new_node(node.left.clone(), node.value.clone(), new_right);
+                (Some(rebalance(grafted)), true)
+            } else {
+                let (new_left, inserted) = insert(&node.left, value);
+                if !inserted {
+                    return (Some(node.clone()), false);
+                }
+                let grafted = new_node(new_left, node.value.clone(), node.right.clone());
+                (Some(rebalance(grafted)), true)
+            }
+        }
+    }
+}
+
+// Removes the smallest value from `node`'s subtree, returning the new
+// subtree alongside the removed value.
+fn take_smallest<T: Ord + Clone>(node: Rc<Node<T>>) -> (Link<T>, T) {
+    match &node.left {
+        None => (node.right.clone(), node.value.clone()),
+        Some(left) => {
+            let (new_left, smallest) = take_smallest(left.clone());
+            let grafted = new_node(new_left, node.value.clone(), node.right.clone());
+            (Some(rebalance(grafted)), smallest)
+        }
+    }
+}
+
+// Returns the new subtree and whether `value` was present and removed.
+fn delete<T: Ord + Clone>(link: &Link<T>, value: &T) -> (Link<T>, bool) {
+    match link {
+        None => (None, false),
+        Some(node) => {
+            if value > &node.value {
+                let (new_right, deleted) = delete(&node.right, value);
+                if !deleted {
+                    return (Some(node.clone()), false);
+                }
+                let grafted = new_node(node.left.clone(), node.value.clone(), new_right);
+                (Some(rebalance(grafted)), true)
+            } else if value < &node.value {
+                let (new_left, deleted) = delete(&node.left, value);
+                if !deleted {
+                    return (Some(node.clone()), false);
+                }
+                let grafted = new_node(new_left, node.value.clone(), node.right.clone());
+                (Some(rebalance(grafted)), true)
+            } else {
+                let new_link = match (&node.left, &node.right) {
+                    (None, None) => None,
+                    (Some(left), None) => Some(left.clone()),
+                    (None, Some(right)) => Some(right.clone()),
+                    (Some(left), Some(right)) => {
+                        let (new_right, smallest) = take_smallest(right.clone());
+                        Some(rebalance(new_node(Some(left.clone()), smallest, new_right)))
+                    }
+                };
+                (new_link, true)
+            }
+        }
+    }
+}
+
+// Persistent (copy-on-write) self-balancing AVL tree: `insert` and `delete`
+// return a new tree that shares every untouched subtree with `self`, so an
+// old version can keep being read from while a new one is built, in
+// O(log n) extra nodes per update rather than a full copy.
+pub struct PersistentBST<T: Ord + Clone> {
+    root: Link<T>,
+    size: u32,
+}
+
+impl<T: Ord + Clone> PersistentBST<T> {
+    pub fn new() -> Self {
+        PersistentBST { root: None, size: 0 }
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        contains(&self.root, value)
+    }
+
+    pub fn insert(&self, value: T) -> PersistentBST<T> {
+        let (root, inserted) = insert(&self.root, value);
+        PersistentBST {
+            root,
+            size: self.size + inserted as u32,
+        }
+    }
+
+    pub fn delete(&self, value: &T) -> PersistentBST<T> {
+        let (root, deleted) = delete(&self.root, value);
+        PersistentBST {
+            root,
+            size: self.size - deleted as u32,
+        }
+    }
+
+    // Returns an independent handle to this version of the tree in O(1): it
+    // shares the whole structure with `self` via the `Rc`-counted root, so
+    // readers can keep using it unaffected by later updates to `self`.
+    pub fn snapshot(&self) -> PersistentBST<T> {
+        PersistentBST {
+            root: self.root.clone(),
+            size: self.size,
+        }
+    }
+}
+
+impl<T: Ord + Clone> Default for PersistentBST<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_returns_a_new_version_leaving_the_old_one_untouched() {
+        let v0 = PersistentBST::new();
+        let v1 = v0.insert(5);
+        assert!(!v0.contains(&5));
+        assert!(v1.contains(&5));
+    }
+
+    #[test]
+    fn delete_returns_a_new_version_without_the_old_one_changing() {
+        let v1 = PersistentBST::new().insert(1).insert(2).insert(3);
+        let v2 = v1.delete(&2);
+        assert!(v1.contains(&2));
+        assert!(!v2.contains(&2));
+    }
+
+    #[test]
+    fn snapshot_keeps_reading_a_historical_version() {
+        let v1 = PersistentBST::new().insert(1).insert(2).insert(3);
+        let snapshot = v1.snapshot();
+        let v2 = v1.insert(4);
+        assert!(!snapshot.contains(&4));
+        assert!(v2.contains(&4));
+        assert!(snapshot.contains(&1) && snapshot.contains(&2) && snapshot.contains(&3));
+    }
+
+    #[test]
+    fn many_versions_each_keep_only_their_own_elements() {
+        let mut tree = PersistentBST::new();
+        for i in 0..50 {
+            tree = tree.insert(i);
+        }
+        for i in (0..50).step_by(2) {
+            tree = tree.delete(&i);
+        }
+        for i in 0..50 {
+            assert_eq!(tree.contains(&i), i % 2 == 1);
+        }
+    }
+}